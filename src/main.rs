@@ -1,20 +1,45 @@
 use axum::{
-    Form, Router,
-    extract::{Path, State},
-    http::StatusCode,
-    response::{Html, IntoResponse, Redirect},
+    extract::{FromRequest, Path, Query, Request, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{Html, IntoResponse, Response},
     routing::{get, post},
+    Form, Json, Router,
 };
+use base64::engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD};
 use base64::Engine;
-use base64::engine::general_purpose::STANDARD;
+use ed25519_dalek::{Signature, VerifyingKey};
+use rand::rngs::OsRng;
+use rand::RngCore;
 use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use sqids::Sqids;
 use sqlx::{
-    FromRow, SqlitePool,
+    error::DatabaseError,
     sqlite::{SqliteConnectOptions, SqlitePoolOptions},
+    FromRow, SqlitePool,
 };
-use std::{env, net::SocketAddr};
+use std::{
+    env,
+    net::SocketAddr,
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use tower_http::cors::CorsLayer;
 use uuid::Uuid;
 
+/// How long a publish challenge's nonce stays valid before it must be
+/// re-issued. Bounds the window an intercepted nonce could be replayed in.
+/// An expired challenge row is deleted only when its token is presented to
+/// `/publish`; nothing else removes it.
+const CHALLENGE_TTL_SECS: u64 = 300;
+
+/// Upper bound on how far out a publisher can set a key's `expires_at`.
+const MAX_KEY_EXPIRY_SECS: u64 = 365 * 24 * 3600;
+
+/// How often the background sweeper checks for and deletes expired keys.
+const EXPIRY_SWEEP_INTERVAL_SECS: u64 = 60;
+
 #[derive(Clone, Debug, Serialize, Deserialize, FromRow)]
 struct PubKeyRecord {
     id: String,
@@ -26,12 +51,112 @@ struct PubKeyRecord {
 struct AppState {
     db: SqlitePool,
     website_name: String,
+    sqids: Arc<Sqids>,
+}
+
+/// How a `/k/:id` path segment resolved: either the new sqids-encoded
+/// `key_id` rowid, or a legacy UUID v4 published before the sqids migration.
+enum RecordLookup {
+    KeyId(i64),
+    LegacyId(String),
+}
+
+#[derive(Deserialize)]
+struct ChallengeForm {
+    public_key: String,
+    note: Option<String>,
+    name: Option<String>,
+    expires_in_secs: Option<String>,
 }
 
 #[derive(Deserialize)]
 struct PublishForm {
+    token: String,
     public_key: String,
     note: Option<String>,
+    name: Option<String>,
+    key_expires_at: Option<String>,
+    signature: String,
+}
+
+#[derive(Deserialize)]
+struct ResolveNameQuery {
+    name: String,
+}
+
+#[derive(Deserialize)]
+struct RevokeForm {
+    token: String,
+}
+
+#[derive(Deserialize)]
+struct UpdateNoteForm {
+    token: String,
+    note: Option<String>,
+}
+
+/// Creates `pub_keys`/`publish_challenges` if missing and brings either up
+/// to the current column set. Shared by `main` (against the real
+/// `pubkeys.db`) and the test suite (against an in-memory database), so the
+/// two never drift apart.
+async fn init_schema(db: &SqlitePool) {
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS pub_keys (
+            key_id INTEGER PRIMARY KEY AUTOINCREMENT,
+            id TEXT UNIQUE,
+            public_key TEXT NOT NULL,
+            note TEXT
+        )
+        "#,
+    )
+    .execute(db)
+    .await
+    .expect("failed to create table");
+
+    migrate_legacy_uuid_schema(db).await;
+
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS publish_challenges (
+            token TEXT PRIMARY KEY,
+            public_key TEXT NOT NULL,
+            note TEXT,
+            nonce TEXT NOT NULL,
+            expires_at INTEGER NOT NULL,
+            consumed INTEGER NOT NULL DEFAULT 0
+        )
+        "#,
+    )
+    .execute(db)
+    .await
+    .expect("failed to create table");
+
+    // `name` is the lookup handle for /.well-known/keys.json; optional and
+    // unique among rows that set it (multiple NULLs are fine).
+    ensure_column(db, "pub_keys", "name", "name TEXT").await;
+    ensure_column(db, "publish_challenges", "name", "name TEXT").await;
+    sqlx::query(
+        "CREATE UNIQUE INDEX IF NOT EXISTS idx_pub_keys_name ON pub_keys(name) WHERE name IS NOT NULL",
+    )
+    .execute(db)
+    .await
+    .expect("failed to create name index");
+
+    // Optional unix-seconds expiry. `key_expires_at` on publish_challenges is
+    // distinct from that table's own `expires_at` (the nonce's TTL).
+    ensure_column(db, "pub_keys", "expires_at", "expires_at INTEGER").await;
+    ensure_column(
+        db,
+        "publish_challenges",
+        "key_expires_at",
+        "key_expires_at INTEGER",
+    )
+    .await;
+
+    // Hash of the one-time management secret shown on the record page right
+    // after publishing; used to authorize /k/:id/revoke and /k/:id/note.
+    ensure_column(db, "pub_keys", "mgmt_token_hash", "mgmt_token_hash TEXT").await;
 }
 
 #[tokio::main]
@@ -49,30 +174,52 @@ async fn main() {
         .await
         .expect("failed to connect to SQLite");
 
-    sqlx::query(
-        r#"
-        CREATE TABLE IF NOT EXISTS pub_keys (
-            id TEXT PRIMARY KEY,
-            public_key TEXT NOT NULL,
-            note TEXT
-        )
-        "#,
-    )
-    .execute(&db)
-    .await
-    .expect("failed to create table");
+    init_schema(&db).await;
 
     // 從環境變量讀取網站名稱，默認為 jiming.cleanyong.familybankbank.com
     let website_name = env::var("WEBSITE_NAME")
         .unwrap_or_else(|_| "jiming.cleanyong.familybankbank.com".to_string());
 
-    let state = AppState { db, website_name };
+    // sqids 編碼設定：可透過環境變量自訂字母表與最短長度，預設給 6 碼短碼。
+    let mut sqids_builder = Sqids::builder();
+    if let Ok(alphabet) = env::var("SQIDS_ALPHABET") {
+        sqids_builder = sqids_builder.alphabet(alphabet.chars().collect());
+    }
+    let sqids_min_length: u8 = env::var("SQIDS_MIN_LENGTH")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(6);
+    let sqids = sqids_builder
+        .min_length(sqids_min_length)
+        .build()
+        .expect("failed to build sqids encoder");
+
+    let state = AppState {
+        db,
+        website_name,
+        sqids: Arc::new(sqids),
+    };
 
     let app = Router::new()
         .route("/", get(show_form))
-        .route("/publish", post(handle_publish))
-        .route("/k/:id", get(show_record))
-        .with_state(state);
+        .route(
+            "/challenge",
+            post(request_challenge).layer(CorsLayer::permissive()),
+        )
+        .route(
+            "/publish",
+            post(handle_publish).layer(CorsLayer::permissive()),
+        )
+        .route("/k/:id", get(show_record).layer(CorsLayer::permissive()))
+        .route("/k/:id/revoke", post(handle_revoke))
+        .route("/k/:id/note", post(handle_update_note))
+        .route(
+            "/.well-known/keys.json",
+            get(resolve_name).layer(CorsLayer::permissive()),
+        )
+        .with_state(state.clone());
+
+    tokio::spawn(sweep_expired_keys(state.db));
 
     // 預設在 127.0.0.1:3003 監聽 (axum 0.7 用 axum::serve)
     let addr = SocketAddr::from(([127, 0, 0, 1], 3003));
@@ -85,6 +232,93 @@ async fn main() {
     axum::serve(listener, app).await.unwrap();
 }
 
+/// Older deployments created `pub_keys` with `id TEXT PRIMARY KEY` as the sole
+/// key, before the sqids-based `key_id` rowid existed. Detect that shape and
+/// rebuild the table, carrying the implicit SQLite rowid over as `key_id` so
+/// existing UUID links keep resolving (see `RecordLookup::LegacyId`).
+async fn migrate_legacy_uuid_schema(db: &SqlitePool) {
+    let has_key_id: i64 = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM pragma_table_info('pub_keys') WHERE name = 'key_id'",
+    )
+    .fetch_one(db)
+    .await
+    .expect("failed to inspect pub_keys schema");
+
+    if has_key_id > 0 {
+        return;
+    }
+
+    let mut tx = db.begin().await.expect("failed to start migration");
+    sqlx::query("ALTER TABLE pub_keys RENAME TO pub_keys_legacy")
+        .execute(&mut *tx)
+        .await
+        .expect("failed to rename legacy table");
+    sqlx::query(
+        r#"
+        CREATE TABLE pub_keys (
+            key_id INTEGER PRIMARY KEY AUTOINCREMENT,
+            id TEXT UNIQUE,
+            public_key TEXT NOT NULL,
+            note TEXT
+        )
+        "#,
+    )
+    .execute(&mut *tx)
+    .await
+    .expect("failed to create migrated table");
+    sqlx::query(
+        "INSERT INTO pub_keys (key_id, id, public_key, note) \
+         SELECT rowid, id, public_key, note FROM pub_keys_legacy",
+    )
+    .execute(&mut *tx)
+    .await
+    .expect("failed to copy legacy rows");
+    sqlx::query("DROP TABLE pub_keys_legacy")
+        .execute(&mut *tx)
+        .await
+        .expect("failed to drop legacy table");
+    tx.commit().await.expect("failed to commit migration");
+}
+
+/// Idempotently adds `column` to `table` via `ALTER TABLE ... ADD COLUMN
+/// {add_clause}` if it isn't already present. `table`/`column`/`add_clause`
+/// are always literals supplied by call sites in this file, never user input.
+async fn ensure_column(db: &SqlitePool, table: &str, column: &str, add_clause: &str) {
+    let check =
+        format!("SELECT COUNT(*) FROM pragma_table_info('{table}') WHERE name = '{column}'");
+    let exists: i64 = sqlx::query_scalar(&check)
+        .fetch_one(db)
+        .await
+        .unwrap_or_else(|e| panic!("failed to inspect {table} schema: {e}"));
+
+    if exists == 0 {
+        sqlx::query(&format!("ALTER TABLE {table} ADD COLUMN {add_clause}"))
+            .execute(db)
+            .await
+            .unwrap_or_else(|e| panic!("failed to add {column} to {table}: {e}"));
+    }
+}
+
+/// Periodically deletes `pub_keys` rows whose `expires_at` has passed, so
+/// short-lived keys don't linger in the database once `show_record` starts
+/// returning 410 Gone for them.
+async fn sweep_expired_keys(db: SqlitePool) {
+    let mut ticker = tokio::time::interval(Duration::from_secs(EXPIRY_SWEEP_INTERVAL_SECS));
+    loop {
+        ticker.tick().await;
+        let now = unix_now() as i64;
+        if let Err(e) = sqlx::query!(
+            "DELETE FROM pub_keys WHERE expires_at IS NOT NULL AND expires_at <= ?",
+            now
+        )
+        .execute(&db)
+        .await
+        {
+            eprintln!("failed to sweep expired keys: {e}");
+        }
+    }
+}
+
 async fn show_form() -> Html<String> {
     let html = r#"
 <!doctype html>
@@ -109,7 +343,7 @@ async fn show_form() -> Html<String> {
     <p class="hint">
       建議使用 ED25519 (EdDSA) 的 public key，一行 Base64 表示。
     </p>
-    <form method="post" action="/publish">
+    <form method="post" action="/challenge">
       <label>
         Public key (required):
         <input type="text" name="public_key" required>
@@ -118,8 +352,19 @@ async fn show_form() -> Html<String> {
         Note / comment (optional):
         <textarea name="note" rows="3" placeholder="例如：這是我用於簽名訊息的公鑰。"></textarea>
       </label>
-      <button type="submit">Publish</button>
+      <label>
+        Name (optional, for /.well-known/keys.json lookups):
+        <input type="text" name="name" placeholder="例如：alice">
+      </label>
+      <label>
+        Expires in (optional, seconds from now):
+        <input type="text" name="expires_in_secs" placeholder="例如：3600 (1 小時後失效)">
+      </label>
+      <button type="submit">Continue</button>
     </form>
+    <p class="hint">
+      下一步會要求你用此公鑰對應的私鑰簽署一組隨機 nonce，證明你確實持有私鑰。
+    </p>
   </body>
 </html>
     "#;
@@ -127,68 +372,757 @@ async fn show_form() -> Html<String> {
     Html(html.to_string())
 }
 
-async fn handle_publish(
-    State(state): State<AppState>,
-    Form(form): Form<PublishForm>,
-) -> impl IntoResponse {
-    let trimmed_key = form.public_key.trim().to_string();
+/// Shared format checks for a submitted base64 ED25519 public key: trims the
+/// value and rejects anything that isn't 32 bytes of valid base64. Does not
+/// check that the bytes are a valid compressed Edwards point — that is left
+/// to `VerifyingKey::from_bytes` at signature-verification time.
+fn validate_public_key_format(raw: &str) -> Result<String, (StatusCode, String)> {
+    let trimmed_key = raw.trim().to_string();
     if trimmed_key.is_empty() {
-        return (StatusCode::BAD_REQUEST, "public_key must not be empty").into_response();
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "public_key must not be empty".to_string(),
+        ));
     }
 
     if trimmed_key
         .chars()
         .any(|c| c.is_control() || c.is_whitespace())
     {
-        return (
+        return Err((
             StatusCode::BAD_REQUEST,
-            "public_key cannot contain whitespace or control characters",
-        )
-            .into_response();
+            "public_key cannot contain whitespace or control characters".to_string(),
+        ));
     }
 
     // key 最長 1000 bytes
     if trimmed_key.as_bytes().len() > 1000 {
-        return (
+        return Err((
             StatusCode::BAD_REQUEST,
-            "public_key must be at most 1000 bytes",
-        )
-            .into_response();
+            "public_key must be at most 1000 bytes".to_string(),
+        ));
     }
 
     // 驗證為 Base64，並且解碼後長度為 32 bytes (ED25519 公鑰)
     match STANDARD.decode(&trimmed_key) {
-        Ok(bytes) if bytes.len() == 32 => {}
-        Ok(_) => {
-            return (
+        Ok(bytes) if bytes.len() == 32 => Ok(trimmed_key),
+        Ok(_) => Err((
+            StatusCode::BAD_REQUEST,
+            "public_key must be base64 of a 32-byte key (ED25519)".to_string(),
+        )),
+        Err(_) => Err((
+            StatusCode::BAD_REQUEST,
+            "public_key must be valid base64".to_string(),
+        )),
+    }
+}
+
+fn validate_note(note: Option<String>) -> Result<Option<String>, (StatusCode, String)> {
+    let note = note.map(|n| n.trim().to_string()).filter(|n| !n.is_empty());
+
+    // note 最長 100 bytes
+    if let Some(ref n) = note {
+        if n.as_bytes().len() > 100 {
+            return Err((
                 StatusCode::BAD_REQUEST,
-                "public_key must be base64 of a 32-byte key (ED25519)",
-            )
-                .into_response();
+                "note must be at most 100 bytes".to_string(),
+            ));
         }
-        Err(_) => {
-            return (StatusCode::BAD_REQUEST, "public_key must be valid base64").into_response();
+    }
+
+    Ok(note)
+}
+
+/// `name` is the handle resolved by `/.well-known/keys.json?name=...`, so it
+/// is kept narrower than a free-text note: lowercase-friendly identifiers
+/// only, short enough to be a sane URL query value.
+fn validate_name(name: Option<String>) -> Result<Option<String>, (StatusCode, String)> {
+    let name = name.map(|n| n.trim().to_string()).filter(|n| !n.is_empty());
+
+    if let Some(ref n) = name {
+        if n.as_bytes().len() > 64 {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                "name must be at most 64 bytes".to_string(),
+            ));
         }
+        if !n
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.')
+        {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                "name may only contain ASCII letters, digits, '-', '_' and '.'".to_string(),
+            ));
+        }
+    }
+
+    Ok(name)
+}
+
+/// Parses the optional "expires in N seconds" form field into an absolute
+/// `expires_at` unix timestamp, bounded by `MAX_KEY_EXPIRY_SECS`.
+fn validate_expiry(raw: Option<String>) -> Result<Option<i64>, (StatusCode, String)> {
+    let raw = raw.map(|s| s.trim().to_string()).filter(|s| !s.is_empty());
+    let Some(raw) = raw else {
+        return Ok(None);
+    };
+
+    let secs: u64 = raw.parse().map_err(|_| {
+        (
+            StatusCode::BAD_REQUEST,
+            "expires_in_secs must be a positive integer".to_string(),
+        )
+    })?;
+
+    if secs == 0 {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "expires_in_secs must be greater than zero".to_string(),
+        ));
+    }
+    if secs > MAX_KEY_EXPIRY_SECS {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!("expires_in_secs must be at most {MAX_KEY_EXPIRY_SECS}"),
+        ));
+    }
+
+    Ok(Some((unix_now() + secs) as i64))
+}
+
+/// The management secret is high-entropy and single-purpose, so a plain
+/// SHA-256 digest (rather than a slow password hash like argon2) is enough
+/// to avoid storing it in recoverable form.
+fn hash_mgmt_token(token: &str) -> String {
+    let digest = Sha256::digest(token.as_bytes());
+    STANDARD.encode(digest)
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("system clock before unix epoch")
+        .as_secs()
+}
+
+/// Step 1 of publishing: issue a single-use nonce bound to the submitted
+/// public key and ask the caller to sign it with the matching private key,
+/// proving they actually control it before anything lands in `pub_keys`.
+async fn request_challenge(
+    State(state): State<AppState>,
+    ContentNegotiatedForm {
+        value: form,
+        is_json,
+    }: ContentNegotiatedForm<ChallengeForm>,
+) -> impl IntoResponse {
+    let trimmed_key = match validate_public_key_format(&form.public_key) {
+        Ok(key) => key,
+        Err((status, msg)) => return negotiated_error(is_json, status, &msg),
+    };
+
+    let note = match validate_note(form.note) {
+        Ok(note) => note,
+        Err((status, msg)) => return negotiated_error(is_json, status, &msg),
+    };
+
+    let name = match validate_name(form.name) {
+        Ok(name) => name,
+        Err((status, msg)) => return negotiated_error(is_json, status, &msg),
+    };
+
+    let key_expires_at = match validate_expiry(form.expires_in_secs) {
+        Ok(expiry) => expiry,
+        Err((status, msg)) => return negotiated_error(is_json, status, &msg),
+    };
+
+    let mut nonce_bytes = [0u8; 32];
+    OsRng.fill_bytes(&mut nonce_bytes);
+    let nonce = STANDARD.encode(nonce_bytes);
+
+    let token = Uuid::new_v4().to_string();
+    let expires_at = (unix_now() + CHALLENGE_TTL_SECS) as i64;
+
+    if let Err(e) = sqlx::query!(
+        "INSERT INTO publish_challenges (token, public_key, note, name, key_expires_at, nonce, expires_at) VALUES (?, ?, ?, ?, ?, ?, ?)",
+        token,
+        trimmed_key,
+        note,
+        name,
+        key_expires_at,
+        nonce,
+        expires_at
+    )
+    .execute(&state.db)
+    .await
+    {
+        return negotiated_error(
+            is_json,
+            StatusCode::INTERNAL_SERVER_ERROR,
+            &format!("database error: {e}"),
+        );
+    }
+
+    // JSON API callers get the token/nonce back directly; the HTML flow
+    // gets the page that walks a human through signing and submitting them.
+    // `key_expires_at` must be echoed verbatim: `handle_publish` requires it
+    // to match the challenge row exactly, and a JSON client has no hidden
+    // form field to read it back from like the HTML flow does.
+    if is_json {
+        return Json(json!({
+            "token": token,
+            "nonce": nonce,
+            "challenge_ttl_secs": CHALLENGE_TTL_SECS,
+            "key_expires_at": key_expires_at.map(|v| v.to_string()),
+        }))
+        .into_response();
+    }
+
+    build_challenge_page(
+        &token,
+        &trimmed_key,
+        note.as_deref(),
+        name.as_deref(),
+        key_expires_at,
+        &nonce,
+    )
+    .into_response()
+}
+
+fn build_challenge_page(
+    token: &str,
+    public_key: &str,
+    note: Option<&str>,
+    name: Option<&str>,
+    key_expires_at: Option<i64>,
+    nonce: &str,
+) -> Html<String> {
+    let html = format!(
+        r#"<!doctype html>
+<html lang="en">
+  <head>
+    <meta charset="utf-8">
+    <title>證明私鑰持有 Prove Key Ownership</title>
+    <style>
+      body {{ font-family: sans-serif; max-width: 640px; margin: 2rem auto; padding: 0 1rem;
+             background-color: #121212; color: #e0e0e0; }}
+      label {{ display: block; margin-top: 1rem; }}
+      textarea, input[type=text] {{ width: 100%; box-sizing: border-box; background-color: #1e1e1e;
+                                   color: #e0e0e0; border: 1px solid #333; border-radius: 4px; padding: 0.4rem; }}
+      button {{ margin-top: 1.5rem; padding: 0.5rem 1.2rem; background-color: #2979ff;
+               color: #fff; border: none; border-radius: 4px; cursor: pointer; }}
+      button:hover {{ background-color: #1565c0; }}
+      code {{ padding: 0.2rem 0.4rem; background: #1e1e1e; border-radius: 4px; }}
+      .hint {{ font-size: 0.9rem; color: #aaa; }}
+    </style>
+  </head>
+  <body>
+    <h1>證明私鑰持有 Prove Key Ownership</h1>
+    <p>請使用與以下公鑰配對的私鑰，對這組 nonce 做 detached ED25519 簽名，並貼上 base64 結果：</p>
+    <p><strong>Nonce:</strong><br><code>{nonce}</code></p>
+    <p class="hint">此 nonce 在 {ttl} 秒內有效，且只能使用一次。</p>
+    <form method="post" action="/publish">
+      <input type="hidden" name="token" value="{token}">
+      <input type="hidden" name="public_key" value="{public_key}">
+      <input type="hidden" name="note" value="{note}">
+      <input type="hidden" name="name" value="{name}">
+      <input type="hidden" name="key_expires_at" value="{key_expires_at}">
+      <label>
+        Signature (base64, required):
+        <input type="text" name="signature" required>
+      </label>
+      <button type="submit">Publish</button>
+    </form>
+  </body>
+</html>
+"#,
+        nonce = html_escape(nonce),
+        ttl = CHALLENGE_TTL_SECS,
+        token = html_escape(token),
+        public_key = html_escape(public_key),
+        note = html_escape(note.unwrap_or("")),
+        name = html_escape(name.unwrap_or("")),
+        key_expires_at = key_expires_at.map(|v| v.to_string()).unwrap_or_default(),
+    );
+
+    Html(html)
+}
+
+/// Builds an error response in plain text or structured JSON (`{"error": "..."}`)
+/// depending on whether the caller is using the HTML/form flow or the JSON API.
+fn negotiated_error(as_json: bool, status: StatusCode, msg: &str) -> Response {
+    if as_json {
+        (status, Json(json!({ "error": msg }))).into_response()
+    } else {
+        (status, msg.to_string()).into_response()
     }
+}
+
+/// `/challenge` and `/publish` both serve the HTML form flow and a JSON API
+/// flow; dispatch on `Content-Type` so an `application/json` body goes
+/// through `Json<T>` and everything else (the usual form post) goes through
+/// `Form<T>`.
+struct ContentNegotiatedForm<T> {
+    value: T,
+    is_json: bool,
+}
+
+impl<T, S> FromRequest<S> for ContentNegotiatedForm<T>
+where
+    T: serde::de::DeserializeOwned + 'static,
+    S: Send + Sync,
+{
+    type Rejection = Response;
 
-    let id = Uuid::new_v4().to_string();
-    let note = form
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let is_json = req
+            .headers()
+            .get(header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .is_some_and(|ct| ct.starts_with("application/json"));
+
+        if is_json {
+            let Json(value) = Json::<T>::from_request(req, state)
+                .await
+                .map_err(|e| negotiated_error(true, StatusCode::BAD_REQUEST, &e.to_string()))?;
+            Ok(Self { value, is_json })
+        } else {
+            let Form(value) = Form::<T>::from_request(req, state)
+                .await
+                .map_err(|e| negotiated_error(false, StatusCode::BAD_REQUEST, &e.to_string()))?;
+            Ok(Self { value, is_json })
+        }
+    }
+}
+
+async fn handle_publish(
+    State(state): State<AppState>,
+    ContentNegotiatedForm {
+        value: form,
+        is_json,
+    }: ContentNegotiatedForm<PublishForm>,
+) -> impl IntoResponse {
+    let challenge = match sqlx::query!(
+        r#"SELECT public_key as "public_key!: String", note as "note?", name as "name?", key_expires_at as "key_expires_at?", nonce as "nonce!: String", expires_at as "expires_at!: i64", consumed as "consumed!: i64" FROM publish_challenges WHERE token = ?"#,
+        form.token
+    )
+    .fetch_optional(&state.db)
+    .await
+    {
+        Ok(Some(row)) => row,
+        Ok(None) => {
+            return negotiated_error(
+                is_json,
+                StatusCode::BAD_REQUEST,
+                "unknown or expired challenge",
+            );
+        }
+        Err(e) => {
+            return negotiated_error(
+                is_json,
+                StatusCode::INTERNAL_SERVER_ERROR,
+                &format!("database error: {e}"),
+            );
+        }
+    };
+
+    // Single-use: an already-consumed or expired challenge cannot be replayed.
+    if challenge.consumed != 0 || challenge.expires_at < unix_now() as i64 {
+        let _ = sqlx::query!("DELETE FROM publish_challenges WHERE token = ?", form.token)
+            .execute(&state.db)
+            .await;
+        return negotiated_error(
+            is_json,
+            StatusCode::BAD_REQUEST,
+            "unknown or expired challenge",
+        );
+    }
+
+    if challenge.public_key != form.public_key.trim() {
+        return negotiated_error(
+            is_json,
+            StatusCode::BAD_REQUEST,
+            "public_key does not match challenge",
+        );
+    }
+
+    let submitted_note = form
         .note
         .map(|n| n.trim().to_string())
         .filter(|n| !n.is_empty());
+    if submitted_note != challenge.note {
+        return negotiated_error(
+            is_json,
+            StatusCode::BAD_REQUEST,
+            "note does not match challenge",
+        );
+    }
+
+    let submitted_name = form
+        .name
+        .map(|n| n.trim().to_string())
+        .filter(|n| !n.is_empty());
+    if submitted_name != challenge.name {
+        return negotiated_error(
+            is_json,
+            StatusCode::BAD_REQUEST,
+            "name does not match challenge",
+        );
+    }
 
-    // note 最長 100 bytes
-    if let Some(ref n) = note {
-        if n.as_bytes().len() > 100 {
-            return (StatusCode::BAD_REQUEST, "note must be at most 100 bytes").into_response();
+    let submitted_key_expires_at: Option<i64> = form
+        .key_expires_at
+        .as_deref()
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .and_then(|s| s.parse().ok());
+    if submitted_key_expires_at != challenge.key_expires_at {
+        return negotiated_error(
+            is_json,
+            StatusCode::BAD_REQUEST,
+            "expires_in_secs does not match challenge",
+        );
+    }
+
+    let key_bytes: [u8; 32] = match STANDARD
+        .decode(&challenge.public_key)
+        .ok()
+        .and_then(|b| b.try_into().ok())
+    {
+        Some(bytes) => bytes,
+        None => {
+            return negotiated_error(
+                is_json,
+                StatusCode::BAD_REQUEST,
+                "public_key must be a 32-byte key",
+            );
+        }
+    };
+    let verifying_key = match VerifyingKey::from_bytes(&key_bytes) {
+        Ok(key) => key,
+        Err(_) => {
+            return negotiated_error(
+                is_json,
+                StatusCode::BAD_REQUEST,
+                "public_key is not a valid ED25519 compressed point",
+            );
+        }
+    };
+
+    let sig_bytes: [u8; 64] = match STANDARD
+        .decode(form.signature.trim())
+        .ok()
+        .and_then(|b| b.try_into().ok())
+    {
+        Some(bytes) => bytes,
+        None => {
+            return negotiated_error(
+                is_json,
+                StatusCode::BAD_REQUEST,
+                "signature must be base64 of a 64-byte ED25519 signature",
+            );
+        }
+    };
+    let signature = Signature::from_bytes(&sig_bytes);
+
+    let nonce_bytes = match STANDARD.decode(&challenge.nonce) {
+        Ok(bytes) => bytes,
+        Err(_) => {
+            return negotiated_error(
+                is_json,
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "stored nonce is not valid base64",
+            );
+        }
+    };
+
+    if verifying_key
+        .verify_strict(&nonce_bytes, &signature)
+        .is_err()
+    {
+        return negotiated_error(
+            is_json,
+            StatusCode::BAD_REQUEST,
+            "signature does not match the challenge nonce",
+        );
+    }
+
+    // Atomically claim the challenge: `consumed = 0` in the WHERE clause
+    // makes this a compare-and-set, so two concurrent /publish calls for the
+    // same token can't both observe it unconsumed and both insert a key.
+    let claim = match sqlx::query!(
+        "UPDATE publish_challenges SET consumed = 1 WHERE token = ? AND consumed = 0",
+        form.token
+    )
+    .execute(&state.db)
+    .await
+    {
+        Ok(result) => result,
+        Err(e) => {
+            return negotiated_error(
+                is_json,
+                StatusCode::INTERNAL_SERVER_ERROR,
+                &format!("database error: {e}"),
+            );
+        }
+    };
+    if claim.rows_affected() != 1 {
+        return negotiated_error(
+            is_json,
+            StatusCode::BAD_REQUEST,
+            "unknown or expired challenge",
+        );
+    }
+
+    let mut mgmt_token_bytes = [0u8; 32];
+    OsRng.fill_bytes(&mut mgmt_token_bytes);
+    let mgmt_token = URL_SAFE_NO_PAD.encode(mgmt_token_bytes);
+    let mgmt_token_hash = hash_mgmt_token(&mgmt_token);
+
+    // Copies of `challenge.public_key`/`.note` for rendering the record page
+    // below.
+    let record_public_key = challenge.public_key.clone();
+    let record_note = challenge.note.clone();
+
+    let insert = sqlx::query!(
+        "INSERT INTO pub_keys (public_key, note, name, expires_at, mgmt_token_hash) VALUES (?, ?, ?, ?, ?)",
+        challenge.public_key,
+        challenge.note,
+        challenge.name,
+        challenge.key_expires_at,
+        mgmt_token_hash
+    )
+    .execute(&state.db)
+    .await;
+
+    let key_id = match insert {
+        Ok(result) => result.last_insert_rowid(),
+        Err(e)
+            if e.as_database_error()
+                .is_some_and(|e| e.is_unique_violation()) =>
+        {
+            return negotiated_error(is_json, StatusCode::CONFLICT, "name is already in use");
+        }
+        Err(e) => {
+            return negotiated_error(
+                is_json,
+                StatusCode::INTERNAL_SERVER_ERROR,
+                &format!("database error: {e}"),
+            );
+        }
+    };
+
+    let _ = sqlx::query!("DELETE FROM publish_challenges WHERE token = ?", form.token)
+        .execute(&state.db)
+        .await;
+
+    let slug = match state.sqids.encode(&[key_id as u64]) {
+        Ok(slug) => slug,
+        Err(e) => {
+            return negotiated_error(
+                is_json,
+                StatusCode::INTERNAL_SERVER_ERROR,
+                &format!("failed to encode record id: {e}"),
+            );
+        }
+    };
+
+    // JSON API callers get the secret back directly in the response body.
+    // The HTML flow renders the record page directly (rather than
+    // redirecting to it) so the one-time secret never lands in a URL —
+    // browser history or an access log would otherwise keep it around
+    // long after "shown once" was supposed to mean once.
+    if is_json {
+        return (
+            StatusCode::CREATED,
+            Json(json!({ "id": slug, "secret": mgmt_token })),
+        )
+            .into_response();
+    }
+
+    let full_url = format!("https://{}/k/{}", state.website_name, slug);
+    let record = PubKeyRecord {
+        id: slug,
+        public_key: record_public_key,
+        note: record_note,
+    };
+    build_record_page(record, Some(&full_url), Some(&mgmt_token)).into_response()
+}
+
+async fn show_record(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    let wants_json = headers
+        .get(header::ACCEPT)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|accept| accept.contains("application/json"));
+
+    let lookup = match validate_record_id(&state.sqids, &id) {
+        Ok(lookup) => lookup,
+        Err((status, msg)) => return negotiated_error(wants_json, status, &msg),
+    };
+
+    let record = match lookup {
+        RecordLookup::KeyId(key_id) => {
+            sqlx::query!(
+                r#"SELECT key_id as "key_id!: i64", id as "id?", public_key as "public_key!: String", note as "note?", expires_at as "expires_at?" FROM pub_keys WHERE key_id = ?"#,
+                key_id
+            )
+            .fetch_optional(&state.db)
+            .await
+            .map(|row| row.map(|r| (r.key_id, r.public_key, r.note, r.expires_at)))
+        }
+        RecordLookup::LegacyId(legacy_id) => {
+            sqlx::query!(
+                r#"SELECT key_id as "key_id!: i64", id as "id?", public_key as "public_key!: String", note as "note?", expires_at as "expires_at?" FROM pub_keys WHERE id = ?"#,
+                legacy_id
+            )
+            .fetch_optional(&state.db)
+            .await
+            .map(|row| row.map(|r| (r.key_id, r.public_key, r.note, r.expires_at)))
+        }
+    };
+
+    match record {
+        Ok(Some((_, _, _, Some(expires_at)))) if expires_at <= unix_now() as i64 => {
+            negotiated_error(wants_json, StatusCode::GONE, "Key has expired")
+        }
+        Ok(Some((key_id, public_key, note, _))) => {
+            let slug = state
+                .sqids
+                .encode(&[key_id as u64])
+                .unwrap_or_else(|_| key_id.to_string());
+            let r = PubKeyRecord {
+                id: slug.clone(),
+                public_key,
+                note,
+            };
+            if wants_json {
+                return Json(r).into_response();
+            }
+            let full_url = format!("https://{}/k/{}", state.website_name, slug);
+            // The management secret is only ever shown once, right at
+            // publish time (see `handle_publish`); it's never re-derivable
+            // from a later GET of this page.
+            build_record_page(r, Some(&full_url), None).into_response()
+        }
+        Ok(None) => negotiated_error(wants_json, StatusCode::NOT_FOUND, "Key not found"),
+        Err(e) => negotiated_error(
+            wants_json,
+            StatusCode::INTERNAL_SERVER_ERROR,
+            &format!("database error: {e}"),
+        ),
+    }
+}
+
+/// Resolves an already-validated `/k/:id` lookup to its `pub_keys.key_id` and
+/// checks the caller's management token against the stored hash before
+/// allowing a revoke or note update. Returns 404 when no such key exists and
+/// 403 when the key has no stored hash or the token does not match, each with
+/// its own message.
+async fn authorize_mgmt_action(
+    state: &AppState,
+    lookup: &RecordLookup,
+    token: &str,
+) -> Result<i64, (StatusCode, String)> {
+    let row = match lookup {
+        RecordLookup::KeyId(key_id) => {
+            sqlx::query!(
+                r#"SELECT key_id as "key_id!: i64", mgmt_token_hash as "mgmt_token_hash?" FROM pub_keys WHERE key_id = ?"#,
+                key_id
+            )
+            .fetch_optional(&state.db)
+            .await
         }
+        RecordLookup::LegacyId(legacy_id) => {
+            sqlx::query!(
+                r#"SELECT key_id as "key_id!: i64", mgmt_token_hash as "mgmt_token_hash?" FROM pub_keys WHERE id = ?"#,
+                legacy_id
+            )
+            .fetch_optional(&state.db)
+            .await
+        }
+    };
+
+    let row = row
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("database error: {e}"),
+            )
+        })?
+        .ok_or((StatusCode::NOT_FOUND, "Key not found".to_string()))?;
+
+    let expected_hash = row.mgmt_token_hash.ok_or((
+        StatusCode::FORBIDDEN,
+        "this key has no management token".to_string(),
+    ))?;
+
+    if hash_mgmt_token(token) != expected_hash {
+        return Err((
+            StatusCode::FORBIDDEN,
+            "invalid management token".to_string(),
+        ));
+    }
+
+    Ok(row.key_id)
+}
+
+async fn handle_revoke(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Form(form): Form<RevokeForm>,
+) -> impl IntoResponse {
+    let lookup = match validate_record_id(&state.sqids, &id) {
+        Ok(lookup) => lookup,
+        Err(resp) => return resp.into_response(),
+    };
+
+    let key_id = match authorize_mgmt_action(&state, &lookup, &form.token).await {
+        Ok(key_id) => key_id,
+        Err(resp) => return resp.into_response(),
+    };
+
+    if let Err(e) = sqlx::query!("DELETE FROM pub_keys WHERE key_id = ?", key_id)
+        .execute(&state.db)
+        .await
+    {
+        return (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("database error: {e}"),
+        )
+            .into_response();
     }
 
+    (StatusCode::OK, "key revoked").into_response()
+}
+
+async fn handle_update_note(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Form(form): Form<UpdateNoteForm>,
+) -> impl IntoResponse {
+    let lookup = match validate_record_id(&state.sqids, &id) {
+        Ok(lookup) => lookup,
+        Err(resp) => return resp.into_response(),
+    };
+
+    let key_id = match authorize_mgmt_action(&state, &lookup, &form.token).await {
+        Ok(key_id) => key_id,
+        Err(resp) => return resp.into_response(),
+    };
+
+    let note = match validate_note(form.note) {
+        Ok(note) => note,
+        Err(resp) => return resp.into_response(),
+    };
+
     if let Err(e) = sqlx::query!(
-        "INSERT INTO pub_keys (id, public_key, note) VALUES (?, ?, ?)",
-        id,
-        trimmed_key,
-        note
+        "UPDATE pub_keys SET note = ? WHERE key_id = ?",
+        note,
+        key_id
     )
     .execute(&state.db)
     .await
@@ -200,48 +1134,91 @@ async fn handle_publish(
             .into_response();
     }
 
-    // 發佈成功後，導向到該 key 的分享頁面
-    Redirect::to(&format!("/k/{id}")).into_response()
+    (StatusCode::OK, "note updated").into_response()
 }
 
-async fn show_record(State(state): State<AppState>, Path(id): Path<String>) -> impl IntoResponse {
-    if let Err(resp) = validate_record_id(&id) {
-        return resp.into_response();
+/// NIP-05-style machine-readable resolution: `GET /.well-known/keys.json?name=<name>`
+/// returns `{"keys": {"<name>": "<base64 pubkey>"}}` for programs that just
+/// want a key, not the HTML record page.
+async fn resolve_name(
+    State(state): State<AppState>,
+    Query(query): Query<ResolveNameQuery>,
+) -> impl IntoResponse {
+    let name = query.name.trim();
+    if name.is_empty() {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(json!({ "error": "name must not be empty" })),
+        )
+            .into_response();
     }
 
-    let record = sqlx::query!(
-        r#"SELECT id as "id!: String", public_key as "public_key!: String", note as "note?" FROM pub_keys WHERE id = ?"#,
-        id
+    // Matches the 410 behavior in `show_record`: an expired key must stop
+    // resolving immediately, not just once the background sweeper gets to it.
+    let now = unix_now() as i64;
+    let row = sqlx::query!(
+        r#"SELECT public_key as "public_key!: String" FROM pub_keys WHERE name = ? AND (expires_at IS NULL OR expires_at > ?)"#,
+        name,
+        now
     )
     .fetch_optional(&state.db)
     .await;
 
-    match record {
-        Ok(Some(r)) => {
-            let r = PubKeyRecord {
-                id: r.id,
-                public_key: r.public_key,
-                note: r.note,
-            };
-            let full_url = format!("https://{}/k/{}", state.website_name, r.id);
-            build_record_page(r, Some(&full_url)).into_response()
-        }
-        Ok(None) => (StatusCode::NOT_FOUND, "Key not found").into_response(),
+    match row {
+        Ok(Some(r)) => Json(json!({ "keys": { name: r.public_key } })).into_response(),
+        Ok(None) => Json(json!({ "keys": {} })).into_response(),
         Err(e) => (
             StatusCode::INTERNAL_SERVER_ERROR,
-            format!("database error: {e}"),
+            Json(json!({ "error": format!("database error: {e}") })),
         )
             .into_response(),
     }
 }
 
-fn build_record_page(record: PubKeyRecord, full_url: Option<&str>) -> Html<String> {
+fn build_record_page(
+    record: PubKeyRecord,
+    full_url: Option<&str>,
+    mgmt_secret: Option<&str>,
+) -> Html<String> {
     let note_html = record
         .note
         .as_deref()
         .map(|n| format!("<p><strong>Note:</strong> {}</p>", html_escape(n)))
         .unwrap_or_else(|| "<p><em>No note provided.</em></p>".to_string());
 
+    let mgmt_html = if let Some(secret) = mgmt_secret {
+        format!(
+            r#"<p><strong>Management secret (shown once, save it now):</strong><br><code>{secret}</code></p>
+<p style="font-size:0.85rem; color:#aaa;">This secret authorizes revoking this key or changing its note below. It will not be shown again.</p>"#,
+            secret = html_escape(secret)
+        )
+    } else {
+        String::new()
+    };
+
+    let manage_forms_html = format!(
+        r#"<h2>Manage this key</h2>
+<form method="post" action="/k/{id}/note">
+  <label>
+    Management secret:
+    <input type="text" name="token" required>
+  </label>
+  <label>
+    New note:
+    <textarea name="note" rows="3"></textarea>
+  </label>
+  <button type="submit">Update note</button>
+</form>
+<form method="post" action="/k/{id}/revoke" style="margin-top:1rem;">
+  <label>
+    Management secret:
+    <input type="text" name="token" required>
+  </label>
+  <button type="submit">Revoke key</button>
+</form>"#,
+        id = record.id
+    );
+
     let link_html = if let Some(url) = full_url {
         format!(
             r#"<p><strong>Shareable link:</strong></p>
@@ -277,9 +1254,11 @@ fn build_record_page(record: PubKeyRecord, full_url: Option<&str>) -> Html<Strin
     <p><strong>ID:</strong> {id}</p>
     <p><strong>Public key:</strong><br><code>{key}</code></p>
     {note}
+    {mgmt}
     {link}
     <hr>
     <p>You can share this link with others so they can obtain your public key.</p>
+    {manage_forms}
     <script>
       function copyLink() {{
         const input = document.getElementById('share-link');
@@ -294,7 +1273,9 @@ fn build_record_page(record: PubKeyRecord, full_url: Option<&str>) -> Html<Strin
         id = record.id,
         key = html_escape(&record.public_key),
         note = note_html,
+        mgmt = mgmt_html,
         link = link_html,
+        manage_forms = manage_forms_html,
     );
 
     Html(html)
@@ -364,14 +1345,137 @@ fn html_escape(s: &str) -> String {
         .collect()
 }
 
-fn validate_record_id(id: &str) -> Result<(), (StatusCode, String)> {
-    // IDs are stored as UUID v4 strings; reject anything that is not a UUID to avoid
-    // accidental SQL injection attempts via the path parameter.
+fn validate_record_id(sqids: &Sqids, id: &str) -> Result<RecordLookup, (StatusCode, String)> {
+    // New-style IDs are sqids-encoded `key_id` rowids. A slug that decodes to
+    // more than one number is rejected outright rather than silently taking
+    // the first/last value; one that decodes to nothing is tried as a legacy
+    // UUID below.
+    let decoded = sqids.decode(id);
+    match decoded.len() {
+        1 => return Ok(RecordLookup::KeyId(decoded[0] as i64)),
+        0 => {}
+        _ => {
+            return Err((StatusCode::BAD_REQUEST, "invalid record id".to_string()));
+        }
+    }
+
+    // Fall back to the legacy UUID v4 scheme for links published before the
+    // sqids migration.
     match Uuid::parse_str(id) {
-        Ok(_) => Ok(()),
-        Err(_) => Err((
-            StatusCode::BAD_REQUEST,
-            "invalid record id (must be a UUID)".to_string(),
-        )),
+        Ok(_) => Ok(RecordLookup::LegacyId(id.to_string())),
+        Err(_) => Err((StatusCode::BAD_REQUEST, "invalid record id".to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ed25519_dalek::{Signer, SigningKey};
+
+    async fn test_state() -> AppState {
+        let db = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect_with(SqliteConnectOptions::new().filename(":memory:"))
+            .await
+            .expect("failed to open in-memory SQLite");
+        init_schema(&db).await;
+
+        AppState {
+            db,
+            website_name: "test.invalid".to_string(),
+            sqids: Arc::new(Sqids::builder().build().expect("failed to build sqids")),
+        }
+    }
+
+    /// Inserts a challenge row directly (bypassing `request_challenge`'s HTTP
+    /// plumbing) and returns its token plus the keypair whose public half it
+    /// was issued for, so a test can sign the nonce and call `handle_publish`
+    /// directly.
+    async fn seed_challenge(state: &AppState) -> (String, SigningKey, String) {
+        let signing_key = SigningKey::generate(&mut OsRng);
+        let public_key = STANDARD.encode(signing_key.verifying_key().to_bytes());
+
+        let mut nonce_bytes = [0u8; 32];
+        OsRng.fill_bytes(&mut nonce_bytes);
+        let nonce = STANDARD.encode(nonce_bytes);
+
+        let token = Uuid::new_v4().to_string();
+        let expires_at = (unix_now() + CHALLENGE_TTL_SECS) as i64;
+
+        sqlx::query!(
+            "INSERT INTO publish_challenges (token, public_key, note, name, key_expires_at, nonce, expires_at) VALUES (?, ?, NULL, NULL, NULL, ?, ?)",
+            token,
+            public_key,
+            nonce,
+            expires_at
+        )
+        .execute(&state.db)
+        .await
+        .expect("failed to seed challenge");
+
+        (token, signing_key, public_key)
+    }
+
+    fn publish_form(token: &str, public_key: &str, signature: &str) -> PublishForm {
+        PublishForm {
+            token: token.to_string(),
+            public_key: public_key.to_string(),
+            note: None,
+            name: None,
+            key_expires_at: None,
+            signature: signature.to_string(),
+        }
+    }
+
+    async fn publish(state: &AppState, form: PublishForm) -> StatusCode {
+        handle_publish(
+            State(state.clone()),
+            ContentNegotiatedForm {
+                value: form,
+                is_json: true,
+            },
+        )
+        .await
+        .into_response()
+        .status()
+    }
+
+    #[tokio::test]
+    async fn tampered_signature_is_rejected() {
+        let state = test_state().await;
+        let (token, _signing_key, public_key) = seed_challenge(&state).await;
+
+        // A signature that was never produced by the challenge's private key.
+        let mut bogus_sig = [0u8; 64];
+        OsRng.fill_bytes(&mut bogus_sig);
+        let signature = STANDARD.encode(bogus_sig);
+
+        let status = publish(&state, publish_form(&token, &public_key, &signature)).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn replayed_challenge_is_rejected() {
+        let state = test_state().await;
+        let (token, signing_key, public_key) = seed_challenge(&state).await;
+
+        let row = sqlx::query!(
+            r#"SELECT nonce as "nonce!: String" FROM publish_challenges WHERE token = ?"#,
+            token
+        )
+        .fetch_one(&state.db)
+        .await
+        .expect("seeded challenge must exist");
+        let nonce_bytes = STANDARD
+            .decode(&row.nonce)
+            .expect("seeded nonce must be base64");
+        let signature = STANDARD.encode(signing_key.sign(&nonce_bytes).to_bytes());
+
+        let first = publish(&state, publish_form(&token, &public_key, &signature)).await;
+        assert_eq!(first, StatusCode::CREATED);
+
+        // Replaying the same token/signature must not mint a second key.
+        let second = publish(&state, publish_form(&token, &public_key, &signature)).await;
+        assert_eq!(second, StatusCode::BAD_REQUEST);
     }
 }